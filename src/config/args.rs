@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use argh::FromArgs;
+
+/// Command line arguments. Overlaid on top of the config file and environment
+/// variables in order of increasing priority: file < env < CLI flags
+#[derive(Debug, FromArgs)]
+pub struct Args {
+    /// path to the config file. Default: `config.yaml`
+    #[argh(option, short = 'C', default = "PathBuf::from(\"config.yaml\")")]
+    pub config: PathBuf,
+
+    /// override `node_settings.adnl_port`
+    #[argh(option)]
+    pub adnl_port: Option<u16>,
+
+    /// override `node_settings.db_path`
+    #[argh(option)]
+    pub db_path: Option<PathBuf>,
+
+    /// override `kafka_settings.raw_transaction_producer.brokers`
+    #[argh(option)]
+    pub brokers: Option<String>,
+
+    /// override `kafka_settings.raw_transaction_producer.topic`
+    #[argh(option)]
+    pub topic: Option<String>,
+}
@@ -2,11 +2,17 @@ use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use ton_indexer::{OldBlocksPolicy, ShardStateCacheOptions};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use ton_indexer::{
+    BlocksGcKind, BlocksGcOptions, OldBlocksPolicy, ShardStateCacheOptions, StateGcOptions,
+};
 
 use self::temp_keys::*;
 
+pub use self::args::Args;
+
+mod args;
+mod size;
 mod temp_keys;
 
 /// Main application config (full). Used to run relay
@@ -28,6 +34,40 @@ pub struct AppConfig {
     pub logger_settings: serde_yaml::Value,
 }
 
+impl AppConfig {
+    /// Loads the config from `args.config`, overlaid with
+    /// `TON_KAFKA__`-prefixed environment variables, and finally with
+    /// explicit CLI flags (highest priority)
+    pub fn load(args: &Args) -> Result<Self> {
+        let mut config: Self = config::Config::builder()
+            .add_source(config::File::from(args.config.as_path()))
+            .add_source(
+                config::Environment::with_prefix("TON_KAFKA")
+                    .separator("__")
+                    .try_parsing(true),
+            )
+            .build()
+            .context("Failed to build config")?
+            .try_deserialize()
+            .context("Failed to parse config")?;
+
+        if let Some(adnl_port) = args.adnl_port {
+            config.node_settings.adnl_port = adnl_port;
+        }
+        if let Some(db_path) = &args.db_path {
+            config.node_settings.db_path = db_path.clone();
+        }
+        if let Some(brokers) = &args.brokers {
+            config.kafka_settings.raw_transaction_producer.brokers = brokers.clone();
+        }
+        if let Some(topic) = &args.topic {
+            config.kafka_settings.raw_transaction_producer.topic = topic.clone();
+        }
+
+        Ok(config)
+    }
+}
+
 /// TON node settings
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(default, deny_unknown_fields)]
@@ -45,13 +85,84 @@ pub struct NodeConfig {
     /// NOTE: generates new keys if specified path doesn't exist
     pub temp_keys_path: PathBuf,
 
-    /// Allowed DB size in bytes. Default: one third of all machine RAM
+    /// Allowed DB size in bytes, or a human-friendly string like `"2GiB"`.
+    /// Default: one third of all machine RAM
+    #[serde(deserialize_with = "size::deserialize_size")]
     pub max_db_memory_usage: usize,
 
     /// Archives map queue. Default: 16
     pub parallel_archive_downloads: u32,
 
     pub start_from: Option<u32>,
+
+    /// State and blocks garbage collection settings. Disabled by default
+    #[serde(default)]
+    pub gc: GcConfig,
+}
+
+/// Garbage collection settings for the RocksDB storage. Disabled by default
+/// to preserve the existing "retain everything" behavior
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct GcConfig {
+    /// Periodically removes old shard states, keeping only what's needed to
+    /// process new blocks. Disabled if `None`
+    pub state_gc: Option<StateGcConfig>,
+    /// Periodically removes old blocks. Disabled if `None`
+    pub blocks_gc: Option<BlocksGcConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct StateGcConfig {
+    /// Time to keep a shard state after it stops being the latest one, in seconds
+    pub offset_sec: u64,
+    /// How often to run the GC, in seconds
+    pub interval_sec: u64,
+}
+
+impl From<StateGcConfig> for StateGcOptions {
+    fn from(config: StateGcConfig) -> Self {
+        Self {
+            offset_sec: config.offset_sec,
+            interval_sec: config.interval_sec,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct BlocksGcConfig {
+    /// When to consider a block old enough to be collected
+    pub kind: BlocksGcKindConfig,
+    /// Whether GC should also run while syncing old blocks
+    #[serde(default)]
+    pub enable_for_sync: bool,
+}
+
+impl From<BlocksGcConfig> for BlocksGcOptions {
+    fn from(config: BlocksGcConfig) -> Self {
+        Self {
+            kind: config.kind.into(),
+            enable_for_sync: config.enable_for_sync,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BlocksGcKindConfig {
+    BeforePreviousKeyBlock,
+    BeforePreviousPersistentState,
+}
+
+impl From<BlocksGcKindConfig> for BlocksGcKind {
+    fn from(kind: BlocksGcKindConfig) -> Self {
+        match kind {
+            BlocksGcKindConfig::BeforePreviousKeyBlock => Self::BeforePreviousKeyBlock,
+            BlocksGcKindConfig::BeforePreviousPersistentState => {
+                Self::BeforePreviousPersistentState
+            }
+        }
+    }
 }
 
 impl NodeConfig {
@@ -84,9 +195,8 @@ impl NodeConfig {
             adnl_keys: temp_keys.into(),
             rocks_db_path: self.db_path.join("rocksdb"),
             file_db_path: self.db_path.join("files"),
-            // NOTE: State GC is disabled until it is fully tested
-            state_gc_options: None,
-            blocks_gc_options: None,
+            state_gc_options: self.gc.state_gc.map(StateGcOptions::from),
+            blocks_gc_options: self.gc.blocks_gc.map(BlocksGcOptions::from),
             shard_state_cache_options: Some(ShardStateCacheOptions::default()),
             archives_enabled: false,
             old_blocks_policy: old_blocks,
@@ -111,6 +221,7 @@ impl Default for NodeConfig {
             max_db_memory_usage: ton_indexer::default_max_db_memory_usage(),
             parallel_archive_downloads: 16,
             start_from: None,
+            gc: GcConfig::default(),
         }
     }
 }
@@ -124,6 +235,10 @@ pub struct StatesConfig {
 #[serde(default)]
 pub struct KafkaConfig {
     pub raw_transaction_producer: KafkaProducerConfig,
+    /// Producer for decoded external/internal messages. Disabled if `None`
+    pub message_producer: Option<KafkaProducerConfig>,
+    /// Producer for raw blocks. Disabled if `None`
+    pub block_producer: Option<KafkaProducerConfig>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -131,10 +246,195 @@ pub struct KafkaProducerConfig {
     pub topic: String,
     pub brokers: String,
     pub message_timeout_ms: Option<u32>,
+    /// Max message size in bytes, or a human-friendly string like `"512MB"`
+    #[serde(default, deserialize_with = "size::deserialize_size_opt")]
     pub message_max_size: Option<usize>,
     pub attempt_interval_ms: u64,
     #[serde(default)]
     pub security_config: Option<SecurityConfig>,
+
+    /// `compression.codec`. Default: whatever rdkafka defaults to (none)
+    #[serde(default)]
+    pub compression_codec: Option<CompressionCodec>,
+    /// `batch.num.messages`
+    #[serde(default)]
+    pub batch_num_messages: Option<u32>,
+    /// `batch.size`
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+    /// `queue.buffering.max.ms`
+    #[serde(default)]
+    pub linger_ms: Option<u32>,
+    /// `request.required.acks`
+    #[serde(default)]
+    pub acks: Option<Acks>,
+    /// `enable.idempotence`
+    #[serde(default)]
+    pub enable_idempotence: Option<bool>,
+    /// `message.send.max.retries`
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// `retry.backoff.ms`
+    #[serde(default)]
+    pub retry_backoff_ms: Option<u32>,
+    /// `queue.buffering.max.messages`
+    #[serde(default)]
+    pub queue_buffering_max_messages: Option<u32>,
+    /// `partitioner`
+    #[serde(default)]
+    pub partitioner: Option<String>,
+
+    /// Arbitrary extra rdkafka producer properties, applied last so they can
+    /// always override the typed fields above
+    #[serde(default)]
+    pub extra: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionCodec {
+    None,
+    Gzip,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+impl CompressionCodec {
+    fn as_rdkafka_value(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+            Self::Snappy => "snappy",
+            Self::Lz4 => "lz4",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+/// `request.required.acks`. Accepts the literal string `"all"` or any
+/// integer (`-1` is Kafka's own synonym for `"all"`).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Acks {
+    All,
+    Exactly(i32),
+}
+
+impl Acks {
+    fn as_rdkafka_value(&self) -> std::borrow::Cow<'static, str> {
+        match self {
+            Self::All => "all".into(),
+            Self::Exactly(n) => n.to_string().into(),
+        }
+    }
+
+    /// `true` for `All` and for its `-1` integer synonym
+    fn is_all(&self) -> bool {
+        matches!(self, Self::All | Self::Exactly(-1))
+    }
+}
+
+impl<'de> Deserialize<'de> for Acks {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Int(i32),
+            Str(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Int(n) => Ok(Acks::Exactly(n)),
+            Repr::Str(s) if s.eq_ignore_ascii_case("all") => Ok(Acks::All),
+            Repr::Str(s) => s
+                .parse::<i32>()
+                .map(Acks::Exactly)
+                .map_err(|_| serde::de::Error::custom(format!("invalid acks value: {s:?}"))),
+        }
+    }
+}
+
+impl Serialize for Acks {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Acks::All => serializer.serialize_str("all"),
+            Acks::Exactly(n) => serializer.serialize_i32(*n),
+        }
+    }
+}
+
+impl KafkaProducerConfig {
+    /// Builds an rdkafka `ClientConfig` from this producer config, translating
+    /// typed fields to their canonical rdkafka property names and applying
+    /// `extra` last so operators can always override.
+    pub fn build_client_config(&self) -> Result<rdkafka::ClientConfig> {
+        if matches!(self.enable_idempotence, Some(true)) {
+            if let Some(acks) = &self.acks {
+                if !acks.is_all() {
+                    return Err(ConfigError::IncompatibleIdempotenceAcks.into());
+                }
+            }
+        }
+
+        let mut config = rdkafka::ClientConfig::new();
+        config.set("bootstrap.servers", &self.brokers);
+
+        if let Some(message_timeout_ms) = self.message_timeout_ms {
+            config.set("message.timeout.ms", message_timeout_ms.to_string());
+        }
+        if let Some(message_max_size) = self.message_max_size {
+            config.set("message.max.bytes", message_max_size.to_string());
+        }
+        if let Some(codec) = &self.compression_codec {
+            config.set("compression.codec", codec.as_rdkafka_value());
+        }
+        if let Some(batch_num_messages) = self.batch_num_messages {
+            config.set("batch.num.messages", batch_num_messages.to_string());
+        }
+        if let Some(batch_size) = self.batch_size {
+            config.set("batch.size", batch_size.to_string());
+        }
+        if let Some(linger_ms) = self.linger_ms {
+            config.set("queue.buffering.max.ms", linger_ms.to_string());
+        }
+        if let Some(acks) = &self.acks {
+            config.set("request.required.acks", acks.as_rdkafka_value().as_ref());
+        }
+        if let Some(enable_idempotence) = self.enable_idempotence {
+            config.set("enable.idempotence", enable_idempotence.to_string());
+        }
+        if let Some(retries) = self.retries {
+            config.set("message.send.max.retries", retries.to_string());
+        }
+        if let Some(retry_backoff_ms) = self.retry_backoff_ms {
+            config.set("retry.backoff.ms", retry_backoff_ms.to_string());
+        }
+        if let Some(queue_buffering_max_messages) = self.queue_buffering_max_messages {
+            config.set(
+                "queue.buffering.max.messages",
+                queue_buffering_max_messages.to_string(),
+            );
+        }
+        if let Some(partitioner) = &self.partitioner {
+            config.set("partitioner", partitioner);
+        }
+
+        if let Some(security_config) = &self.security_config {
+            security_config.apply(&mut config)?;
+        }
+
+        for (key, value) in &self.extra {
+            config.set(key, value);
+        }
+
+        Ok(config)
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
@@ -142,13 +442,191 @@ pub enum SecurityConfig {
     Sasl(SaslConfig),
 }
 
+impl SecurityConfig {
+    fn apply(&self, config: &mut rdkafka::ClientConfig) -> Result<()> {
+        match self {
+            Self::Sasl(sasl) => sasl.apply(config),
+        }
+    }
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Default, Debug, Clone)]
+#[serde(default)]
 pub struct SaslConfig {
     pub security_protocol: String,
     pub ssl_ca_location: String,
     pub sasl_mechanism: String,
     pub sasl_username: String,
-    pub sasl_password: String,
+    /// Plaintext password. Prefer `sasl_password_env` or `sasl_password_file`
+    pub sasl_password: Option<String>,
+    /// Name of an environment variable to read the password from
+    pub sasl_password_env: Option<String>,
+    /// Path to a file containing the password. Refused if the file is
+    /// group- or world-readable, unless `allow_world_readable_secrets` is set
+    pub sasl_password_file: Option<PathBuf>,
+    /// Escape hatch for static-config deployments where the permission check
+    /// gets in the way. Also honored via the `TON_KAFKA_ALLOW_WORLD_READABLE_SECRETS`
+    /// env var, which always takes precedence over this field
+    pub allow_world_readable_secrets: bool,
+}
+
+impl SaslConfig {
+    fn apply(&self, config: &mut rdkafka::ClientConfig) -> Result<()> {
+        config.set("security.protocol", &self.security_protocol);
+        config.set("ssl.ca.location", &self.ssl_ca_location);
+        config.set("sasl.mechanism", &self.sasl_mechanism);
+        config.set("sasl.username", &self.sasl_username);
+        config.set("sasl.password", self.resolve_password()?);
+        Ok(())
+    }
+
+    /// Resolves the SASL password from, in order of precedence: a file path,
+    /// an environment variable, or the plaintext config value.
+    fn resolve_password(&self) -> Result<String> {
+        if let Some(path) = &self.sasl_password_file {
+            self.check_file_permissions(path)?;
+            let password = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read SASL password from {:?}", path))?;
+            return Ok(password.trim_end_matches(['\n', '\r']).to_owned());
+        }
+
+        if let Some(env_var) = &self.sasl_password_env {
+            return std::env::var(env_var)
+                .with_context(|| format!("Failed to read SASL password from env var {env_var}"));
+        }
+
+        self.sasl_password
+            .clone()
+            .ok_or_else(|| ConfigError::MissingSaslPassword.into())
+    }
+
+    #[cfg(unix)]
+    fn check_file_permissions(&self, path: &Path) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if self.allow_world_readable_secrets_effective() {
+            return Ok(());
+        }
+
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("Failed to stat SASL password file {:?}", path))?;
+        let mode = metadata.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(ConfigError::WorldReadableSecret(path.to_owned()).into());
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn check_file_permissions(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn allow_world_readable_secrets_effective(&self) -> bool {
+        match std::env::var("TON_KAFKA_ALLOW_WORLD_READABLE_SECRETS") {
+            Ok(value) => value == "1" || value.eq_ignore_ascii_case("true"),
+            Err(_) => self.allow_world_readable_secrets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod sasl_test {
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Mutex;
+
+    use super::SaslConfig;
+
+    // `TON_KAFKA_ALLOW_WORLD_READABLE_SECRETS` is process-global, so tests
+    // that touch it must not run concurrently with each other.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn write_secret_file(unique: &str, contents: &str, mode: u32) -> std::path::PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "ton-kafka-producer-test-secret-{}-{unique}",
+            std::process::id()
+        ));
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[test]
+    fn resolves_password_with_file_env_plaintext_precedence() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::remove_var("TON_KAFKA_ALLOW_WORLD_READABLE_SECRETS");
+
+        let mut config = SaslConfig {
+            sasl_password: Some("plaintext".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(config.resolve_password().unwrap(), "plaintext");
+
+        config.sasl_password_env = Some("TON_KAFKA_TEST_SASL_PASSWORD".to_owned());
+        std::env::set_var("TON_KAFKA_TEST_SASL_PASSWORD", "from-env");
+        assert_eq!(config.resolve_password().unwrap(), "from-env");
+
+        let path = write_secret_file("precedence", "from-file\n", 0o600);
+        config.sasl_password_file = Some(path.clone());
+        assert_eq!(config.resolve_password().unwrap(), "from-file");
+
+        std::env::remove_var("TON_KAFKA_TEST_SASL_PASSWORD");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn refuses_world_readable_secret_file() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::remove_var("TON_KAFKA_ALLOW_WORLD_READABLE_SECRETS");
+
+        let path = write_secret_file("refuse", "secret", 0o644);
+        let config = SaslConfig {
+            sasl_password_file: Some(path.clone()),
+            ..Default::default()
+        };
+        assert!(config.resolve_password().is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn allow_world_readable_secrets_field_permits_world_readable_file() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        std::env::remove_var("TON_KAFKA_ALLOW_WORLD_READABLE_SECRETS");
+
+        let path = write_secret_file("allow-field", "secret", 0o644);
+        let config = SaslConfig {
+            sasl_password_file: Some(path.clone()),
+            allow_world_readable_secrets: true,
+            ..Default::default()
+        };
+        assert_eq!(config.resolve_password().unwrap(), "secret");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn env_var_takes_precedence_over_config_field() {
+        let _guard = ENV_LOCK.lock().unwrap();
+
+        let path = write_secret_file("env-precedence", "secret", 0o644);
+        let config = SaslConfig {
+            sasl_password_file: Some(path.clone()),
+            allow_world_readable_secrets: true,
+            ..Default::default()
+        };
+
+        // Env var explicitly disallows, overriding the `true` config field
+        std::env::set_var("TON_KAFKA_ALLOW_WORLD_READABLE_SECRETS", "false");
+        assert!(config.resolve_password().is_err());
+        std::env::remove_var("TON_KAFKA_ALLOW_WORLD_READABLE_SECRETS");
+
+        std::fs::remove_file(path).unwrap();
+    }
 }
 
 impl ConfigExt for ton_indexer::GlobalConfig {
@@ -194,6 +672,12 @@ fn default_logger_settings() -> serde_yaml::Value {
 enum ConfigError {
     #[error("Failed to find public ip")]
     PublicIpNotFound,
+    #[error("enable_idempotence requires acks = all")]
+    IncompatibleIdempotenceAcks,
+    #[error("No SASL password configured (set sasl_password, sasl_password_env or sasl_password_file)")]
+    MissingSaslPassword,
+    #[error("Refusing to read secret from group- or world-accessible file {0:?} (set allow_world_readable_secrets to override)")]
+    WorldReadableSecret(PathBuf),
 }
 
 #[cfg(test)]
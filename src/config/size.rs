@@ -0,0 +1,99 @@
+//! Human-friendly byte size parsing for config fields such as
+//! `max_db_memory_usage` and `message_max_size`.
+
+use serde::{Deserialize, Deserializer};
+
+/// Parses a size value that's either a raw byte count or a human-friendly
+/// string such as `"2GiB"` / `"512MB"` (binary KiB/MiB/GiB/TiB or decimal
+/// KB/MB/GB/TB suffixes).
+pub fn deserialize_size<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SizeOrString {
+        Size(usize),
+        String(String),
+    }
+
+    match SizeOrString::deserialize(deserializer)? {
+        SizeOrString::Size(size) => Ok(size),
+        SizeOrString::String(s) => parse_size(&s).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Same as [`deserialize_size`] but for an `Option<usize>` field.
+pub fn deserialize_size_opt<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SizeOrString {
+        Size(Option<usize>),
+        String(String),
+    }
+
+    match SizeOrString::deserialize(deserializer)? {
+        SizeOrString::Size(size) => Ok(size),
+        SizeOrString::String(s) => parse_size(&s).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+const BINARY_UNITS: [(&str, u64); 4] = [
+    ("kib", 1024),
+    ("mib", 1024 * 1024),
+    ("gib", 1024 * 1024 * 1024),
+    ("tib", 1024 * 1024 * 1024 * 1024),
+];
+
+const DECIMAL_UNITS: [(&str, u64); 4] = [
+    ("kb", 1_000),
+    ("mb", 1_000_000),
+    ("gb", 1_000_000_000),
+    ("tb", 1_000_000_000_000),
+];
+
+fn parse_size(s: &str) -> Result<usize, String> {
+    let trimmed = s.trim();
+    let lower = trimmed.to_ascii_lowercase();
+
+    for (suffix, multiplier) in BINARY_UNITS.iter().chain(DECIMAL_UNITS.iter()) {
+        if let Some(number) = lower.strip_suffix(suffix) {
+            let number: f64 = number
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid size value: {trimmed:?}"))?;
+            if !number.is_finite() || number < 0.0 {
+                return Err(format!("invalid size value: {trimmed:?}"));
+            }
+            return Ok((number * *multiplier as f64) as usize);
+        }
+    }
+
+    lower
+        .parse::<usize>()
+        .map_err(|_| format!("invalid size value: {trimmed:?}"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_plain_and_suffixed_sizes() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("2KiB").unwrap(), 2048);
+        assert_eq!(parse_size("512MB").unwrap(), 512_000_000);
+        assert_eq!(parse_size("1GiB").unwrap(), 1024 * 1024 * 1024);
+        assert!(parse_size("not a size").is_err());
+    }
+
+    #[test]
+    fn rejects_negative_and_non_finite_sizes() {
+        assert!(parse_size("-1GiB").is_err());
+        assert!(parse_size("infGiB").is_err());
+        assert!(parse_size("nanGiB").is_err());
+    }
+}
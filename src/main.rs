@@ -0,0 +1,17 @@
+use anyhow::Result;
+use ton_kafka_producer::config::{AppConfig, Args};
+use ton_kafka_producer::producer::KafkaProducers;
+use ton_kafka_producer::subscriber::KafkaSubscriber;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Args = argh::from_env();
+    let config = AppConfig::load(&args)?;
+
+    let producers = KafkaProducers::new(&config.kafka_settings)?;
+    let _subscriber = KafkaSubscriber::new(producers);
+    let _node_config = config.node_settings.build_indexer_config().await?;
+
+    log::info!("Starting ton-kafka-producer");
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+
+use crate::config::{KafkaConfig, KafkaProducerConfig};
+
+/// One rdkafka producer bound to a single topic.
+struct Producer {
+    topic: String,
+    producer: FutureProducer,
+}
+
+impl Producer {
+    fn new(config: &KafkaProducerConfig) -> Result<Self> {
+        Ok(Self {
+            topic: config.topic.clone(),
+            producer: config.build_client_config()?.create()?,
+        })
+    }
+
+    async fn send(&self, key: &[u8], payload: &[u8]) -> Result<()> {
+        let record = FutureRecord::to(&self.topic).key(key).payload(payload);
+        self.producer
+            .send(record, Timeout::Never)
+            .await
+            .map_err(|(err, _)| err)?;
+        Ok(())
+    }
+}
+
+/// Fan-out over the independently-configurable Kafka producers: raw
+/// transactions, decoded messages, and raw blocks. `raw_transaction_producer`
+/// is always built; `message_producer`/`block_producer` are only built when
+/// present in the config, and sending to a disabled stream is a no-op.
+#[derive(Clone)]
+pub struct KafkaProducers {
+    raw_transaction_producer: Arc<Producer>,
+    message_producer: Option<Arc<Producer>>,
+    block_producer: Option<Arc<Producer>>,
+}
+
+impl KafkaProducers {
+    pub fn new(config: &KafkaConfig) -> Result<Self> {
+        Ok(Self {
+            raw_transaction_producer: Arc::new(Producer::new(&config.raw_transaction_producer)?),
+            message_producer: config
+                .message_producer
+                .as_ref()
+                .map(Producer::new)
+                .transpose()?
+                .map(Arc::new),
+            block_producer: config
+                .block_producer
+                .as_ref()
+                .map(Producer::new)
+                .transpose()?
+                .map(Arc::new),
+        })
+    }
+
+    /// Sends a decoded transaction to the raw transaction stream
+    pub async fn send_transaction(&self, key: &[u8], payload: &[u8]) -> Result<()> {
+        self.raw_transaction_producer.send(key, payload).await
+    }
+
+    /// Sends a decoded message to the message stream, if configured
+    pub async fn send_message(&self, key: &[u8], payload: &[u8]) -> Result<()> {
+        match &self.message_producer {
+            Some(producer) => producer.send(key, payload).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Sends a raw block to the block stream, if configured
+    pub async fn send_block(&self, key: &[u8], payload: &[u8]) -> Result<()> {
+        match &self.block_producer {
+            Some(producer) => producer.send(key, payload).await,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use ton_block::{Deserializable, HashmapAugType};
+use ton_indexer::{ProcessBlockContext, Subscriber};
+
+use crate::producer::KafkaProducers;
+
+/// Fans each indexed block out to whichever Kafka producers are configured:
+/// every transaction is always sent to `raw_transaction_producer`; each
+/// transaction's inbound message and the raw block itself are only
+/// published if their producer was enabled in the config.
+pub struct KafkaSubscriber {
+    producers: KafkaProducers,
+}
+
+impl KafkaSubscriber {
+    pub fn new(producers: KafkaProducers) -> Self {
+        Self { producers }
+    }
+}
+
+#[async_trait]
+impl Subscriber for KafkaSubscriber {
+    async fn process_block(&self, ctx: ProcessBlockContext<'_>) -> Result<()> {
+        let block_id = ctx.id();
+
+        if let Some(block_data) = ctx.block_data() {
+            self.producers
+                .send_block(block_id.root_hash.as_slice(), block_data)
+                .await?;
+        }
+
+        let block = ctx.block_stuff().block();
+        let extra = block.read_extra()?;
+        let account_blocks = extra.read_account_blocks()?;
+
+        let mut transactions = Vec::new();
+        account_blocks.iterate_objects(|account_block| {
+            for entry in account_block.transactions().iter() {
+                let (_, transaction_cell) = entry?;
+                let cell = transaction_cell.into_cell()?;
+                let transaction = ton_block::Transaction::construct_from_cell(cell.clone())?;
+                let account_id = transaction.account_id().get_bytestring(0);
+                let transaction_bytes = ton_types::boc::write_boc(&cell)?;
+                let message_bytes = match transaction.in_msg_cell() {
+                    Some(in_msg_cell) => Some(ton_types::boc::write_boc(&in_msg_cell)?),
+                    None => None,
+                };
+                transactions.push((account_id, transaction_bytes, message_bytes));
+            }
+            Ok(true)
+        })?;
+
+        for (account_id, transaction_bytes, message_bytes) in transactions {
+            self.producers
+                .send_transaction(&account_id, &transaction_bytes)
+                .await?;
+            if let Some(message_bytes) = &message_bytes {
+                self.producers.send_message(&account_id, message_bytes).await?;
+            }
+        }
+
+        Ok(())
+    }
+}